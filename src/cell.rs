@@ -0,0 +1,172 @@
+//! Per-cell styling: colors, attribute flags, and the "pen" that tracks the
+//! currently active SGR state while the grid is being written to.
+
+/// A single cell in the terminal grid: a character plus the style it was written with.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Color,
+    pub bg: Color,
+    pub flags: CellFlags,
+    /// `true` if this cell is the second column of a width-2 glyph written into the
+    /// preceding cell; such cells carry no content of their own and are skipped on output.
+    pub continuation: bool,
+    /// Zero-width combining characters that were written while this cell was the cursor's
+    /// previous position; rendered immediately after `ch`.
+    pub combining: Vec<char>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            fg: Color::Default,
+            bg: Color::Default,
+            flags: CellFlags::default(),
+            continuation: false,
+            combining: Vec::new(),
+        }
+    }
+}
+
+/// A foreground or background color, as set by an SGR sequence.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Color {
+    /// The terminal's default foreground/background color
+    #[default]
+    Default,
+    /// One of the 256 indexed colors (0-15 are the classic 16 ANSI colors)
+    Indexed(u8),
+    /// A 24-bit truecolor value
+    Rgb(u8, u8, u8),
+}
+
+/// Boolean SGR attributes that apply to a cell.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CellFlags {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+/// The current SGR state, stamped onto each cell as it's written.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Pen {
+    pub fg: Color,
+    pub bg: Color,
+    pub flags: CellFlags,
+}
+
+impl Pen {
+    /// Apply the SGR parameters from a `TermCmd::Sgr`, as a mini state machine over `params`
+    /// (needed because `38`/`48` consume their own trailing sub-parameters). Each entry is
+    /// `None` where the corresponding `;`-separated field was empty, e.g. the unused colorspace
+    /// id some terminals send as `CSI 38;2;;r;g;bm`. Anywhere else an empty field acts like
+    /// `0` and resets the pen; keeping it as an entry, rather than dropping it, keeps later
+    /// params aligned instead of shifting everything after them.
+    pub fn apply_sgr(&mut self, params: &[Option<u8>]) {
+        if params.is_empty() {
+            *self = Self::default();
+            return;
+        }
+        let mut iter = params.iter().copied();
+        while let Some(p) = iter.next() {
+            match p {
+                None | Some(0) => *self = Self::default(),
+                Some(1) => self.flags.bold = true,
+                Some(3) => self.flags.italic = true,
+                Some(4) => self.flags.underline = true,
+                Some(7) => self.flags.reverse = true,
+                Some(22) => self.flags.bold = false,
+                Some(23) => self.flags.italic = false,
+                Some(24) => self.flags.underline = false,
+                Some(27) => self.flags.reverse = false,
+                Some(n @ 30..=37) => self.fg = Color::Indexed(n - 30),
+                Some(n @ 40..=47) => self.bg = Color::Indexed(n - 40),
+                Some(n @ 90..=97) => self.fg = Color::Indexed(n - 90 + 8),
+                Some(n @ 100..=107) => self.bg = Color::Indexed(n - 100 + 8),
+                Some(39) => self.fg = Color::Default,
+                Some(49) => self.bg = Color::Default,
+                Some(38) => Self::apply_extended_color(&mut iter, &mut self.fg),
+                Some(48) => Self::apply_extended_color(&mut iter, &mut self.bg),
+                _ => {}
+            }
+        }
+    }
+    /// Parse the `5;n` (indexed) or `2;r;g;b` (truecolor) forms that follow a `38`/`48` param.
+    /// The truecolor form also accepts an empty colorspace-id placeholder before `r` (as in
+    /// `38;2;;r;g;b`), skipping it rather than misreading it as `r`.
+    fn apply_extended_color(iter: &mut impl Iterator<Item = Option<u8>>, color: &mut Color) {
+        match iter.next().flatten() {
+            Some(5) => {
+                if let Some(n) = iter.next().flatten() {
+                    *color = Color::Indexed(n);
+                }
+            }
+            Some(2) => {
+                let (r, g, b) = match iter.next() {
+                    Some(None) => (
+                        iter.next().flatten(),
+                        iter.next().flatten(),
+                        iter.next().flatten(),
+                    ),
+                    Some(Some(r)) => (Some(r), iter.next().flatten(), iter.next().flatten()),
+                    None => (None, None, None),
+                };
+                if let (Some(r), Some(g), Some(b)) = (r, g, b) {
+                    *color = Color::Rgb(r, g, b);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, Pen};
+
+    #[test]
+    fn extended_rgb_color() {
+        let mut pen = Pen::default();
+        pen.apply_sgr(&[Some(38), Some(2), Some(10), Some(20), Some(30)]);
+        assert_eq!(pen.fg, Color::Rgb(10, 20, 30));
+    }
+
+    #[test]
+    fn extended_rgb_color_with_empty_colorspace_id_stays_aligned() {
+        // Some terminals send an unused colorspace-id field before r;g;b, e.g.
+        // `CSI 38;2;;10;20;30m`; it must be skipped, not misread as `r`, and params after it
+        // (here, bold) must not be shifted out of place.
+        let mut pen = Pen::default();
+        pen.apply_sgr(&[
+            Some(38),
+            Some(2),
+            None,
+            Some(10),
+            Some(20),
+            Some(30),
+            Some(1),
+        ]);
+        assert_eq!(pen.fg, Color::Rgb(10, 20, 30));
+        assert!(pen.flags.bold);
+    }
+
+    #[test]
+    fn indexed_extended_color() {
+        let mut pen = Pen::default();
+        pen.apply_sgr(&[Some(48), Some(5), Some(200)]);
+        assert_eq!(pen.bg, Color::Indexed(200));
+    }
+
+    #[test]
+    fn empty_param_resets_like_zero() {
+        let mut pen = Pen::default();
+        pen.apply_sgr(&[Some(1)]);
+        assert!(pen.flags.bold);
+        pen.apply_sgr(&[None]);
+        assert!(!pen.flags.bold);
+    }
+}
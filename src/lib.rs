@@ -8,9 +8,14 @@
 
 //! A simple, minimal ANSI terminal emulator whose contents can be get as a string.
 
+mod cell;
 mod parser;
+mod snapshot;
+mod term_state;
 
-use parser::{AnsiParser, TermCmd};
+pub use parser::{AnsiParser, Handler};
+pub use snapshot::Snapshot;
+use term_state::{Cursor, TermState};
 
 /// Minimalistic ANSI terminal emulator.
 ///
@@ -18,86 +23,17 @@ use parser::{AnsiParser, TermCmd};
 ///
 /// [`feed`]: Self::feed
 /// [`contents_to_string`]: Self::contents_to_string
+#[allow(clippy::struct_field_names)]
 pub struct Term {
     term_state: TermState,
     ansi_parser: AnsiParser,
-}
-
-struct TermState {
-    width: u16,
-    height: usize,
-    cells: Vec<char>,
-    cursor: Cursor,
-}
-
-impl TermState {
-    fn new(width: u16) -> Self {
-        Self {
-            width,
-            height: 0,
-            cells: Vec::new(),
-            cursor: Cursor::default(),
-        }
-    }
-    fn contents_to_string(&self) -> String {
-        let mut buf = String::with_capacity(self.width as usize * self.height);
-        for y in 0..self.height {
-            buf.extend(self.line_slice(y));
-            buf.push('\n');
-        }
-        buf
-    }
-    fn line_slice(&self, y: usize) -> &[char] {
-        let from = y * self.width as usize;
-        let to = from + self.width as usize;
-        &self.cells[from..to]
-    }
-    fn put_char(&mut self, ch: char) {
-        self.extend_while_cursor_past();
-        self.cells[self.cursor.index(self.width)] = ch;
-        self.cursor.x += 1;
-        if self.cursor.x >= self.width {
-            self.cursor.x = 0;
-            self.cursor.y += 1;
-        }
-    }
-    fn extend(&mut self) {
-        self.cells
-            .extend(std::iter::repeat_n(' ', self.width as usize));
-        self.height += 1;
-    }
-    fn extend_while_cursor_past(&mut self) {
-        while self.cursor.y >= self.height {
-            self.extend();
-        }
-    }
-    fn erase_from_cursor_to_eol(&mut self) {
-        for x in self.cursor.x..self.width {
-            let idx = self.cursor.y * self.width as usize + x as usize;
-            if idx >= self.cells.len() {
-                break;
-            }
-            self.cells[idx] = ' ';
-        }
-    }
-    fn clear(&mut self, mode: u8) {
-        if mode != 2 {
-            log::warn!("Clear mode {mode} not implemented.");
-        }
-        self.cells.fill(' ');
-    }
-}
-
-#[derive(Default)]
-struct Cursor {
-    x: u16,
-    y: usize,
-}
-
-impl Cursor {
-    const fn index(&self, width: u16) -> usize {
-        self.y * width as usize + self.x as usize
-    }
+    /// The primary screen's state, parked here while [`enter_alt_screen`](Handler::enter_alt_screen)
+    /// has `term_state` showing the alternate screen instead. `None` means we're on the primary
+    /// screen.
+    saved_primary: Option<Box<TermState>>,
+    /// The cursor position saved by [`save_cursor`](Handler::save_cursor) (`ESC 7`), restored by
+    /// [`restore_cursor`](Handler::restore_cursor) (`ESC 8`).
+    saved_cursor: Option<Cursor>,
 }
 
 impl Term {
@@ -107,57 +43,250 @@ impl Term {
         Self {
             term_state: TermState::new(width),
             ansi_parser: AnsiParser::default(),
+            saved_primary: None,
+            saved_cursor: None,
+        }
+    }
+    /// Create a terminal with a fixed visible `height` and a scrollback buffer bounded to
+    /// `scrollback` lines. Lines that scroll past the visible region are moved into
+    /// scrollback, dropping the oldest once `scrollback` is exceeded, instead of growing
+    /// memory without bound.
+    #[must_use]
+    pub fn with_dimensions(width: u16, height: usize, scrollback: usize) -> Self {
+        Self {
+            term_state: TermState::with_dimensions(width, height, scrollback),
+            ansi_parser: AnsiParser::default(),
+            saved_primary: None,
+            saved_cursor: None,
         }
     }
     /// Feed bytes to the terminal, updating its state
     pub fn feed(&mut self, data: &[u8]) {
-        self.ansi_parser.advance(data, |cmd| match cmd {
-            TermCmd::PutChar(c) => self.term_state.put_char(c),
-            TermCmd::CarriageReturn => self.term_state.cursor.x = 0,
-            TermCmd::LineFeed => self.term_state.cursor.y += 1,
-            TermCmd::CursorUp(n) => {
-                self.term_state.cursor.y = self.term_state.cursor.y.saturating_sub(n as usize);
-            }
-            TermCmd::CursorDown(n) => {
-                self.term_state.cursor.y += n as usize;
-            }
-            TermCmd::CursorLeft(n) => {
-                self.term_state.cursor.x = self.term_state.cursor.x.saturating_sub(u16::from(n));
-            }
-            TermCmd::CursorRight(n) => {
-                self.term_state.cursor.x += u16::from(n);
-            }
-            TermCmd::CursorCrUp(n) => {
-                self.term_state.cursor.y = self.term_state.cursor.y.saturating_sub(n as usize);
-                self.term_state.cursor.x = 0;
-            }
-            TermCmd::CursorCrDown(n) => {
-                self.term_state.cursor.y += n as usize;
-                self.term_state.cursor.x = 0;
-            }
-            TermCmd::CursorSet { x, y } => {
-                self.term_state.cursor.x = x.into();
-                self.term_state.cursor.y = y as usize;
-            }
-            TermCmd::EraseFromCursorToEol => self.term_state.erase_from_cursor_to_eol(),
-            TermCmd::Clear(mode) => self.term_state.clear(mode),
-        });
+        let mut ansi_parser = std::mem::take(&mut self.ansi_parser);
+        ansi_parser.advance(data, self);
+        self.ansi_parser = ansi_parser;
     }
     /// Completely reset the terminal to its initial state
     pub fn reset(&mut self) {
-        self.term_state.cursor = Cursor::default();
-        self.term_state.cells.clear();
-        self.term_state.height = 0;
-        self.ansi_parser = AnsiParser::default();
+        // On the alternate screen, the primary screen holds the configured scrollback size.
+        let state = self.saved_primary.as_deref().unwrap_or(&self.term_state);
+        let width = state.width;
+        *self = match state.height_limit() {
+            Some(height) => Self::with_dimensions(width, height, state.max_scrollback()),
+            None => Self::new(width),
+        };
     }
     /// Get the contents of the terminal as a string
     #[must_use]
     pub fn contents_to_string(&self) -> String {
         self.term_state.contents_to_string()
     }
+    /// Get the contents of the terminal as a string, with minimal ANSI escape sequences
+    /// re-emitted to preserve the styling (colors, bold, italic, underline, reverse) that was
+    /// written to each cell.
+    #[must_use]
+    pub fn contents_to_ansi(&self) -> String {
+        self.term_state.contents_to_ansi()
+    }
+    /// Get the contents of the terminal as an HTML `<pre>` block, styled with inline `<span>`s.
+    #[must_use]
+    pub fn contents_to_html(&self) -> String {
+        self.term_state.contents_to_html()
+    }
+    /// Get just the on-screen rows as a string, ignoring scrollback (unless [`set_scroll_offset`]
+    /// has paged the viewport up into history).
+    ///
+    /// [`set_scroll_offset`]: Self::set_scroll_offset
+    #[must_use]
+    pub fn visible_contents_to_string(&self) -> String {
+        self.term_state.visible_contents_to_string()
+    }
+    /// How many lines are currently held in scrollback
+    #[must_use]
+    pub fn scrollback_lines(&self) -> usize {
+        self.term_state.scrollback_lines()
+    }
+    /// How far up from the bottom [`visible_contents_to_string`] currently pages
+    ///
+    /// [`visible_contents_to_string`]: Self::visible_contents_to_string
+    #[must_use]
+    pub const fn scroll_offset(&self) -> usize {
+        self.term_state.scroll_offset()
+    }
+    /// Page the viewport returned by [`visible_contents_to_string`] up into scrollback by
+    /// `offset` lines, clamped to the amount of scrollback available. `0` shows the live
+    /// viewport.
+    ///
+    /// [`visible_contents_to_string`]: Self::visible_contents_to_string
+    pub fn set_scroll_offset(&mut self, offset: usize) {
+        self.term_state.set_scroll_offset(offset);
+    }
+    /// Capture the current visible grid, to later be diffed against with [`render_diff`].
+    ///
+    /// [`render_diff`]: Self::render_diff
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        self.term_state.snapshot()
+    }
+    /// Compute the minimal ANSI byte stream (cursor moves plus only the changed runs) that
+    /// repaints a real terminal showing `prev` so it matches the current state, instead of
+    /// requiring a full redraw every frame.
+    ///
+    /// Must be called exactly once per [`snapshot`], in order: the rows a call diffs are also
+    /// marked clean by that same call, so calling this twice against the same `prev` returns
+    /// the real diff once and an empty buffer the second time, even though the grid may still
+    /// differ from `prev`. Always take a fresh `snapshot()` right after each `render_diff`
+    /// call and pass it to the next one.
+    ///
+    /// [`snapshot`]: Self::snapshot
+    pub fn render_diff(&mut self, prev: &Snapshot) -> Vec<u8> {
+        self.term_state.render_diff(prev)
+    }
     /// Returns whether the terminal buffer is "empty" (nothing has been written to it yet)
     #[must_use]
-    pub const fn is_empty(&self) -> bool {
-        self.term_state.cells.is_empty()
+    pub fn is_empty(&self) -> bool {
+        self.term_state.is_empty()
+    }
+}
+
+impl Handler for Term {
+    fn put_char(&mut self, c: char) {
+        self.term_state.put_char(c);
+    }
+    fn carriage_return(&mut self) {
+        self.term_state.carriage_return();
+    }
+    fn line_feed(&mut self) {
+        self.term_state.line_feed();
+    }
+    fn cursor_up(&mut self, n: u8) {
+        self.term_state.cursor_up(n);
+    }
+    fn cursor_down(&mut self, n: u8) {
+        self.term_state.cursor_down(n);
+    }
+    fn cursor_left(&mut self, n: u8) {
+        self.term_state.cursor_left(n);
+    }
+    fn cursor_right(&mut self, n: u8) {
+        self.term_state.cursor_right(n);
+    }
+    fn cursor_cr_down(&mut self, n: u8) {
+        self.term_state.cursor_cr_down(n);
+    }
+    fn cursor_cr_up(&mut self, n: u8) {
+        self.term_state.cursor_cr_up(n);
+    }
+    fn cursor_set(&mut self, x: u8, y: u8) {
+        self.term_state.cursor_set(x, y);
+    }
+    fn erase_in_line(&mut self, mode: u8) {
+        self.term_state.erase_in_line(mode);
+    }
+    fn clear(&mut self, mode: u8) {
+        self.term_state.clear(mode);
+    }
+    fn sgr(&mut self, params: &[Option<u8>]) {
+        self.term_state.sgr(params);
+    }
+    /// Save the cursor position, mirroring real terminals' `ESC 7` (DECSC): a later `ESC 8`
+    /// restores it even across an intervening alt-screen switch.
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some(self.term_state.cursor);
+    }
+    /// Restore the cursor position saved by a previous `ESC 7`. A no-op if nothing was saved.
+    fn restore_cursor(&mut self) {
+        if let Some(cursor) = self.saved_cursor {
+            self.term_state.cursor = cursor;
+        }
+    }
+    /// Switch to the alternate screen buffer, parking the primary screen's grid aside. The
+    /// alternate screen starts out blank, matching how real terminals use this for full-screen
+    /// apps (pagers, editors) that shouldn't disturb the scrollback they're invoked from.
+    ///
+    /// A nested `enter_alt_screen` while already on the alternate screen is a no-op: there's
+    /// only one "primary" to return to.
+    fn enter_alt_screen(&mut self) {
+        if self.saved_primary.is_some() {
+            return;
+        }
+        let width = self.term_state.width;
+        let blank = self.term_state.height_limit().map_or_else(
+            || TermState::new(width),
+            |height| TermState::with_dimensions(width, height, 0),
+        );
+        self.saved_primary = Some(Box::new(std::mem::replace(&mut self.term_state, blank)));
+    }
+    /// Switch back to the primary screen buffer, exactly as it was before
+    /// [`enter_alt_screen`](Handler::enter_alt_screen), discarding anything drawn on the
+    /// alternate screen. A no-op if we're not currently on the alternate screen.
+    fn leave_alt_screen(&mut self) {
+        if let Some(primary) = self.saved_primary.take() {
+            self.term_state = *primary;
+        }
+    }
+    fn unhandled(&mut self, final_byte: u8, params: &[u8]) {
+        TermState::log_unhandled(final_byte, params);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Term;
+
+    #[test]
+    fn with_dimensions_is_empty_until_something_is_written() {
+        let mut term = Term::with_dimensions(5, 3, 10);
+        assert!(term.is_empty());
+        term.feed(b"x");
+        assert!(!term.is_empty());
+    }
+
+    #[test]
+    fn alt_screen_restores_primary_content_on_leave() {
+        let mut term = Term::new(5);
+        term.feed(b"abc");
+        term.feed(b"\x1b[?1049h"); // enter alt screen
+        assert!(
+            term.contents_to_string().trim_end().is_empty(),
+            "alt screen must start out blank"
+        );
+        term.feed(b"xy");
+        term.feed(b"\x1b[?1049l"); // leave alt screen
+        assert!(term.contents_to_string().contains("abc"));
+    }
+
+    #[test]
+    fn nested_enter_alt_screen_is_a_noop() {
+        let mut term = Term::new(5);
+        term.feed(b"abc");
+        term.feed(b"\x1b[?1049h");
+        term.feed(b"xy");
+        // A second enter while already on the alt screen must not stash the alt screen itself
+        // as a second "primary": a single leave afterwards should already restore "abc", rather
+        // than requiring a matching second leave.
+        term.feed(b"\x1b[?1049h");
+        term.feed(b"\x1b[?1049l");
+        assert!(term.contents_to_string().contains("abc"));
+    }
+
+    #[test]
+    fn save_and_restore_cursor_round_trips_position() {
+        let mut term = Term::new(5);
+        term.feed(b"ab"); // cursor now at column 2
+        term.feed(b"\x1b7"); // DECSC: save cursor
+        term.feed(b"\x1b[1;1H"); // move cursor to column 1, row 1
+        term.feed(b"\x1b8"); // DECRC: restore cursor
+        term.feed(b"c");
+        assert_eq!(term.contents_to_string().trim_end(), "abc");
+    }
+
+    #[test]
+    fn restore_cursor_without_prior_save_is_a_noop() {
+        let mut term = Term::new(5);
+        term.feed(b"ab");
+        term.feed(b"\x1b8"); // nothing was saved, so this must not move the cursor
+        term.feed(b"c");
+        assert_eq!(term.contents_to_string().trim_end(), "abc");
     }
 }
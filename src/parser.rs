@@ -2,15 +2,75 @@ use std::iter::zip;
 
 const ESC: char = 0x1b as char;
 
+/// Parses a stream of bytes as ANSI escape sequences, invoking methods on a [`Handler`] for
+/// each recognized command.
 #[derive(Debug)]
 pub struct AnsiParser {
     status: Status,
     param_bytes: Vec<u8>,
 }
 
+/// Receives terminal control commands as [`AnsiParser::advance`] decodes them from a byte
+/// stream.
+///
+/// [`Term`](crate::Term) implements this over its own grid, but the parser doesn't know or
+/// care about that: implement `Handler` yourself to drive a different screen model, a logger
+/// that just records what was sent, or a test harness, all on top of the same escape-sequence
+/// decoding.
+pub trait Handler {
+    /// A printable character was received
+    fn put_char(&mut self, c: char);
+    /// `\r`
+    fn carriage_return(&mut self);
+    /// `\n`
+    fn line_feed(&mut self);
+    /// Move cursor up this many lines
+    fn cursor_up(&mut self, n: u8);
+    /// Move cursor down this many lines
+    fn cursor_down(&mut self, n: u8);
+    /// Move cursor left this many columns
+    fn cursor_left(&mut self, n: u8);
+    /// Move cursor right this many columns
+    fn cursor_right(&mut self, n: u8);
+    /// Beginning of next line, this many lines down
+    fn cursor_cr_down(&mut self, n: u8);
+    /// Beginning of previous line, this many lines up
+    fn cursor_cr_up(&mut self, n: u8);
+    /// Set the cursor to (x, y)
+    fn cursor_set(&mut self, x: u8, y: u8);
+    /// Erase part of the cursor's line (`CSI K`): `0` from the cursor to the end of the line,
+    /// `1` from the start of the line to the cursor (inclusive), `2` the whole line.
+    fn erase_in_line(&mut self, mode: u8);
+    /// Erase part of the screen (`CSI J`): `0` from the cursor to the end of the screen, `1`
+    /// from the start of the screen to the cursor, `2` the whole visible screen, `3` the whole
+    /// visible screen plus scrollback.
+    fn clear(&mut self, mode: u8);
+    /// Select Graphic Rendition: apply the semicolon-separated parameters of a `CSI ... m`.
+    /// An entry is `None` where the corresponding field was empty (e.g. an omitted colorspace
+    /// id), rather than that field being dropped and shifting every later one.
+    fn sgr(&mut self, params: &[Option<u8>]);
+    /// Begin a synchronized update (`CSI ?2026h`)
+    fn begin_sync_update(&mut self) {}
+    /// End a synchronized update (`CSI ?2026l`)
+    fn end_sync_update(&mut self) {}
+    /// Save the cursor position (`ESC 7`, DECSC)
+    fn save_cursor(&mut self) {}
+    /// Restore the previously saved cursor position (`ESC 8`, DECRC)
+    fn restore_cursor(&mut self) {}
+    /// Switch to the alternate screen buffer (`CSI ?1049h` / `CSI ?47h`)
+    fn enter_alt_screen(&mut self) {}
+    /// Switch back to the primary screen buffer (`CSI ?1049l` / `CSI ?47l`)
+    fn leave_alt_screen(&mut self) {}
+    /// A recognized control sequence with no handler-specific meaning was encountered
+    fn unhandled(&mut self, final_byte: u8, params: &[u8]) {
+        let _ = (final_byte, params);
+    }
+}
+
 trait ParamBytesExt {
     fn parse_first(&self) -> Option<u8>;
     fn parse<const N: usize>(&self) -> Option<[u8; N]>;
+    fn parse_list(&self) -> Vec<Option<u8>>;
 }
 
 impl ParamBytesExt for Vec<u8> {
@@ -29,6 +89,12 @@ impl ParamBytesExt for Vec<u8> {
         }
         Some(arr)
     }
+    fn parse_list(&self) -> Vec<Option<u8>> {
+        let Ok(s) = std::str::from_utf8(self) else {
+            return Vec::new();
+        };
+        s.split(';').map(|arg| arg.parse().ok()).collect()
+    }
 }
 
 impl Default for AnsiParser {
@@ -47,50 +113,18 @@ enum Status {
     ControlSeqStart,
 }
 
-#[derive(Debug)]
-pub enum TermCmd {
-    PutChar(char),
-    CarriageReturn,
-    LineFeed,
-    /// Move cursor up this many lines
-    CursorUp(u8),
-    /// Move cursor down this many lines
-    CursorDown(u8),
-    /// Move cursor left this many columns
-    CursorLeft(u8),
-    /// Move cursor right this many columns
-    CursorRight(u8),
-    /// Beginning of line, this many lines down
-    CursorCrDown(u8),
-    /// Beginning of line, this many lines up
-    CursorCrUp(u8),
-    /// Set the cursor to (x, y)
-    CursorSet {
-        x: u8,
-        y: u8,
-    },
-    /// Erase from cursor to the end of line
-    EraseFromCursorToEol,
-    /// Clear the screen, in the manner specified by the argument
-    Clear(u8),
-    /// Begin synchronized update
-    BeginSyncUpdate,
-    /// End synchronized update
-    EndSyncUpdate,
-}
-
 impl AnsiParser {
-    pub fn advance(&mut self, bytes: &[u8], mut term_callback: impl FnMut(TermCmd)) {
+    /// Advance the parser by feeding it more bytes, calling `handler` for each command
+    /// recognized along the way.
+    pub fn advance(&mut self, bytes: &[u8], handler: &mut impl Handler) {
         for chnk in bytes.utf8_chunks() {
             for ch in chnk.valid().chars() {
                 match self.status {
                     Status::Init => match ch {
                         ESC => self.status = Status::Esc,
-                        '\r' => {
-                            term_callback(TermCmd::CarriageReturn);
-                        }
-                        '\n' => term_callback(TermCmd::LineFeed),
-                        c => term_callback(TermCmd::PutChar(c)),
+                        '\r' => handler.carriage_return(),
+                        '\n' => handler.line_feed(),
+                        c => handler.put_char(c),
                     },
                     Status::Esc => {
                         match ch {
@@ -102,6 +136,16 @@ impl AnsiParser {
                                 // Control sequence start
                                 self.status = Status::ControlSeqStart;
                             }
+                            '7' => {
+                                // DECSC: save cursor
+                                handler.save_cursor();
+                                self.status = Status::Init;
+                            }
+                            '8' => {
+                                // DECRC: restore cursor
+                                handler.restore_cursor();
+                                self.status = Status::Init;
+                            }
                             _ => log::error!("Unexpected ansi [{:x}]", ch as u32),
                         }
                     }
@@ -112,68 +156,7 @@ impl AnsiParser {
                             }
                             0x40..=0x7E => {
                                 // Terminator byte
-                                match ch {
-                                    // color/etc, ignore
-                                    'm' => {}
-                                    'K' => {
-                                        term_callback(TermCmd::EraseFromCursorToEol);
-                                    }
-                                    'A' => {
-                                        // Move cursor up N lines
-                                        let n = self.param_bytes.parse_first();
-                                        term_callback(TermCmd::CursorUp(n.unwrap_or(1)));
-                                    }
-                                    'B' => {
-                                        // Move down N lines
-                                        let n = self.param_bytes.parse_first();
-                                        term_callback(TermCmd::CursorDown(n.unwrap_or(1)));
-                                    }
-                                    'C' => {
-                                        // Move cursor right N columns
-                                        let n = self.param_bytes.parse_first();
-                                        term_callback(TermCmd::CursorRight(n.unwrap_or(1)));
-                                    }
-                                    'D' => {
-                                        // Move cursor left N columns
-                                        let n = self.param_bytes.parse_first();
-                                        term_callback(TermCmd::CursorLeft(n.unwrap_or(1)));
-                                    }
-                                    'E' => {
-                                        // Beginning of next line, N lines down
-                                        let n = self.param_bytes.parse_first();
-                                        term_callback(TermCmd::CursorCrDown(n.unwrap_or(1)));
-                                    }
-                                    'F' => {
-                                        // Beginning of prev line, N lines up
-                                        let n = self.param_bytes.parse_first();
-                                        term_callback(TermCmd::CursorCrUp(n.unwrap_or(1)));
-                                    }
-                                    'H' => {
-                                        let [x, y] = self.param_bytes.parse().unwrap_or([1, 1]);
-                                        term_callback(TermCmd::CursorSet { x, y });
-                                    }
-                                    'J' => {
-                                        let mode = self.param_bytes.parse_first().unwrap_or(2);
-                                        term_callback(TermCmd::Clear(mode));
-                                    }
-                                    'h' => {
-                                        if self.param_bytes == b"?2026" {
-                                            term_callback(TermCmd::BeginSyncUpdate);
-                                        }
-                                    }
-                                    'l' => {
-                                        if self.param_bytes == b"?2026" {
-                                            term_callback(TermCmd::EndSyncUpdate);
-                                        }
-                                    }
-                                    etc => {
-                                        log::warn!(
-                                            "Ignored control code: '{ch}', params: {params:?}",
-                                            ch = etc,
-                                            params = std::str::from_utf8(&self.param_bytes)
-                                        );
-                                    }
-                                }
+                                self.dispatch(ch, handler);
                                 self.status = Status::Init;
                                 self.param_bytes.clear();
                             }
@@ -184,4 +167,64 @@ impl AnsiParser {
             }
         }
     }
+    fn dispatch(&self, final_byte: char, handler: &mut impl Handler) {
+        match final_byte {
+            'm' => handler.sgr(&self.param_bytes.parse_list()),
+            'K' => {
+                let mode = self.param_bytes.parse_first().unwrap_or(0);
+                handler.erase_in_line(mode);
+            }
+            'A' => {
+                // Move cursor up N lines
+                let n = self.param_bytes.parse_first();
+                handler.cursor_up(n.unwrap_or(1));
+            }
+            'B' => {
+                // Move down N lines
+                let n = self.param_bytes.parse_first();
+                handler.cursor_down(n.unwrap_or(1));
+            }
+            'C' => {
+                // Move cursor right N columns
+                let n = self.param_bytes.parse_first();
+                handler.cursor_right(n.unwrap_or(1));
+            }
+            'D' => {
+                // Move cursor left N columns
+                let n = self.param_bytes.parse_first();
+                handler.cursor_left(n.unwrap_or(1));
+            }
+            'E' => {
+                // Beginning of next line, N lines down
+                let n = self.param_bytes.parse_first();
+                handler.cursor_cr_down(n.unwrap_or(1));
+            }
+            'F' => {
+                // Beginning of prev line, N lines up
+                let n = self.param_bytes.parse_first();
+                handler.cursor_cr_up(n.unwrap_or(1));
+            }
+            'H' => {
+                let [x, y] = self.param_bytes.parse().unwrap_or([1, 1]);
+                handler.cursor_set(x, y);
+            }
+            'J' => {
+                let mode = self.param_bytes.parse_first().unwrap_or(2);
+                handler.clear(mode);
+            }
+            'h' => match self.param_bytes.as_slice() {
+                b"?2026" => handler.begin_sync_update(),
+                b"?1049" | b"?47" => handler.enter_alt_screen(),
+                _ => {}
+            },
+            'l' => match self.param_bytes.as_slice() {
+                b"?2026" => handler.end_sync_update(),
+                b"?1049" | b"?47" => handler.leave_alt_screen(),
+                _ => {}
+            },
+            etc => {
+                handler.unhandled(etc as u8, &self.param_bytes);
+            }
+        }
+    }
 }
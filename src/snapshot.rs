@@ -0,0 +1,88 @@
+//! Snapshot/diff rendering: capturing the visible grid at a point in time, and computing the
+//! minimal ANSI byte stream that transforms one snapshot into the current grid.
+
+use crate::cell::{Cell, Pen};
+use std::fmt::Write as _;
+
+/// An opaque, owned copy of a terminal's visible grid, taken with [`Term::snapshot`] and later
+/// passed to [`Term::render_diff`].
+///
+/// [`Term::snapshot`]: crate::Term::snapshot
+/// [`Term::render_diff`]: crate::Term::render_diff
+pub struct Snapshot {
+    width: u16,
+    cells: Vec<Cell>,
+}
+
+impl Snapshot {
+    pub(crate) const fn capture(width: u16, cells: Vec<Cell>) -> Self {
+        Self { width, cells }
+    }
+    /// The row at `y`, or `None` if `width` doesn't match (nothing meaningful to diff against)
+    /// or the row is out of bounds.
+    pub(crate) fn line(&self, width: u16, y: usize) -> Option<&[Cell]> {
+        if self.width != width {
+            return None;
+        }
+        let w = width as usize;
+        self.cells.get(y * w..y * w + w)
+    }
+}
+
+/// Appends the minimal `CSI row;colH` moves and cell runs that turn `prev` (the row at screen
+/// row `y` the last time it was snapshotted, if any) into `cur`, followed by `CSI K` if a
+/// trailing region was cleared.
+pub fn diff_row(buf: &mut String, y: usize, cur: &[Cell], prev: Option<&[Cell]>) {
+    let content_end = cur
+        .iter()
+        .rposition(|c| *c != Cell::default())
+        .map_or(0, |i| i + 1);
+
+    let matches = |x: usize| prev.and_then(|p| p.get(x)).is_some_and(|p| *p == cur[x]);
+
+    let mut x = 0;
+    while x < content_end {
+        if matches(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < content_end && !matches(x) {
+            x += 1;
+        }
+        push_cursor_set(buf, start, y);
+        push_run(buf, &cur[start..x]);
+    }
+
+    if (content_end..cur.len()).any(|x| !matches(x)) {
+        push_cursor_set(buf, content_end, y);
+        buf.push_str("\x1b[K");
+    }
+}
+
+fn push_cursor_set(buf: &mut String, x: usize, y: usize) {
+    let _ = write!(buf, "\x1b[{};{}H", y + 1, x + 1);
+}
+
+/// Writes a run of cells, re-emitting SGR only where the style changes within the run. The
+/// style is always set explicitly before the first cell, since the terminal's actual current
+/// attribute state after the preceding `CSI H` move is otherwise unknown.
+fn push_run(buf: &mut String, cells: &[Cell]) {
+    let mut current = None;
+    for cell in cells {
+        if cell.continuation {
+            continue;
+        }
+        let pen = Pen {
+            fg: cell.fg,
+            bg: cell.bg,
+            flags: cell.flags,
+        };
+        if Some(pen) != current {
+            crate::term_state::push_sgr(buf, pen);
+            current = Some(pen);
+        }
+        buf.push(cell.ch);
+        buf.extend(cell.combining.iter().copied());
+    }
+}
@@ -0,0 +1,683 @@
+//! The terminal grid itself: cells, cursor, and the operations the parser drives it with.
+
+use crate::cell::{Cell, CellFlags, Color, Pen};
+use crate::snapshot::{Snapshot, diff_row};
+use std::collections::VecDeque;
+use unicode_width::UnicodeWidthChar;
+
+pub struct TermState {
+    pub width: u16,
+    pub height: usize,
+    /// `Some(height)` once the terminal has a fixed visible height (created via
+    /// [`Self::with_dimensions`]): rows that scroll past it move into `scrollback` instead of
+    /// growing `cells` without bound. `None` keeps the legacy unbounded-growth behavior of
+    /// [`Self::new`].
+    height_limit: Option<usize>,
+    pub cells: Vec<Cell>,
+    scrollback: VecDeque<Vec<Cell>>,
+    max_scrollback: usize,
+    scroll_offset: usize,
+    pub cursor: Cursor,
+    pub pen: Pen,
+    /// One flag per visible row, set whenever that row is written to and cleared by
+    /// [`Self::render_diff`] once it's been accounted for.
+    dirty: Vec<bool>,
+}
+
+impl TermState {
+    pub fn new(width: u16) -> Self {
+        Self {
+            width,
+            height: 0,
+            height_limit: None,
+            cells: Vec::new(),
+            scrollback: VecDeque::new(),
+            max_scrollback: 0,
+            scroll_offset: 0,
+            cursor: Cursor::default(),
+            pen: Pen::default(),
+            dirty: Vec::new(),
+        }
+    }
+    /// Create a terminal with a fixed visible `height` and a scrollback buffer bounded to
+    /// `max_scrollback` lines.
+    pub fn with_dimensions(width: u16, height: usize, max_scrollback: usize) -> Self {
+        Self {
+            width,
+            height,
+            height_limit: Some(height),
+            cells: vec![Cell::default(); width as usize * height],
+            scrollback: VecDeque::new(),
+            max_scrollback,
+            scroll_offset: 0,
+            cursor: Cursor::default(),
+            pen: Pen::default(),
+            dirty: vec![true; height],
+        }
+    }
+    /// Capture the current visible grid for later use with [`Self::render_diff`].
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::capture(self.width, self.cells.clone())
+    }
+    /// Whether nothing has been written to this terminal yet. `cells` is empty in the
+    /// unbounded-height mode `new` constructs, but `with_dimensions` pre-fills it to blank
+    /// default cells up front, so emptiness has to be judged by content, not length.
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|cell| *cell == Cell::default())
+    }
+    /// Compute the minimal ANSI byte stream that turns `prev` into the current grid, skipping
+    /// any row whose dirty flag isn't set (i.e. unchanged since the last call).
+    ///
+    /// The dirty flags this consults are cleared as a side effect of every call, not scoped to
+    /// `prev` specifically: calling this twice with the *same* `prev` (without a fresh
+    /// [`Self::snapshot`] and intervening mutation in between) returns the real diff the first
+    /// time and an empty one the second, because the rows it would have re-diffed were already
+    /// marked clean. Callers must take a new snapshot via `snapshot()` immediately after each
+    /// `render_diff` call, pass it to the next call, and call it exactly once per snapshot, in
+    /// order.
+    pub fn render_diff(&mut self, prev: &Snapshot) -> Vec<u8> {
+        let mut buf = String::new();
+        for y in 0..self.height {
+            if !self.dirty.get(y).copied().unwrap_or(true) {
+                continue;
+            }
+            diff_row(&mut buf, y, self.line_slice(y), prev.line(self.width, y));
+        }
+        for dirty in &mut self.dirty {
+            *dirty = false;
+        }
+        buf.into_bytes()
+    }
+    fn mark_dirty(&mut self, y: usize) {
+        if let Some(dirty) = self.dirty.get_mut(y) {
+            *dirty = true;
+        }
+    }
+    pub const fn height_limit(&self) -> Option<usize> {
+        self.height_limit
+    }
+    pub const fn max_scrollback(&self) -> usize {
+        self.max_scrollback
+    }
+    pub fn contents_to_string(&self) -> String {
+        let mut buf =
+            String::with_capacity(self.width as usize * (self.height + self.scrollback.len()));
+        for line in self.all_lines() {
+            push_line(&mut buf, line);
+            buf.push('\n');
+        }
+        buf
+    }
+    /// Like [`Self::contents_to_string`], but re-emits minimal SGR escape sequences to
+    /// preserve styling.
+    pub fn contents_to_ansi(&self) -> String {
+        let mut buf =
+            String::with_capacity(self.width as usize * (self.height + self.scrollback.len()));
+        let mut current = Pen::default();
+        for line in self.all_lines() {
+            for cell in line {
+                if cell.continuation {
+                    continue;
+                }
+                let pen = Pen {
+                    fg: cell.fg,
+                    bg: cell.bg,
+                    flags: cell.flags,
+                };
+                if pen != current {
+                    push_sgr(&mut buf, pen);
+                    current = pen;
+                }
+                buf.push(cell.ch);
+                buf.extend(cell.combining.iter().copied());
+            }
+            buf.push('\n');
+        }
+        if current != Pen::default() {
+            buf.push_str("\x1b[0m");
+        }
+        buf
+    }
+    /// Renders the contents as an HTML `<pre>` block, with one `<span style="...">` per run
+    /// of differently-styled cells.
+    pub fn contents_to_html(&self) -> String {
+        let mut buf = String::from("<pre>");
+        for line in self.all_lines() {
+            let mut current: Option<String> = None;
+            for cell in line {
+                if cell.continuation {
+                    continue;
+                }
+                let style = cell_style(cell);
+                if style != current {
+                    if current.is_some() {
+                        buf.push_str("</span>");
+                    }
+                    if let Some(style) = &style {
+                        buf.push_str("<span style=\"");
+                        buf.push_str(style);
+                        buf.push_str("\">");
+                    }
+                    current = style;
+                }
+                push_html_escaped(&mut buf, cell.ch);
+                for c in &cell.combining {
+                    push_html_escaped(&mut buf, *c);
+                }
+            }
+            if current.is_some() {
+                buf.push_str("</span>");
+            }
+            buf.push('\n');
+        }
+        buf.push_str("</pre>");
+        buf
+    }
+    /// Just the on-screen rows, i.e. `height` rows starting `scroll_offset` lines up from the
+    /// bottom of the combined scrollback-plus-viewport buffer.
+    pub fn visible_contents_to_string(&self) -> String {
+        let mut buf = String::with_capacity(self.width as usize * self.height);
+        let start = self.scrollback.len() - self.scroll_offset;
+        for i in 0..self.height {
+            let line_no = start + i;
+            if let Some(line) = self.scrollback.get(line_no) {
+                push_line(&mut buf, line);
+            } else {
+                push_line(&mut buf, self.line_slice(line_no - self.scrollback.len()));
+            }
+            buf.push('\n');
+        }
+        buf
+    }
+    /// How many lines are currently held in scrollback.
+    pub fn scrollback_lines(&self) -> usize {
+        self.scrollback.len()
+    }
+    pub const fn scroll_offset(&self) -> usize {
+        self.scroll_offset
+    }
+    /// Set how far up from the bottom [`Self::visible_contents_to_string`] pages, clamped to
+    /// the amount of scrollback available.
+    pub fn set_scroll_offset(&mut self, offset: usize) {
+        self.scroll_offset = offset.min(self.scrollback.len());
+    }
+    fn all_lines(&self) -> impl Iterator<Item = &[Cell]> {
+        self.scrollback
+            .iter()
+            .map(Vec::as_slice)
+            .chain((0..self.height).map(|y| self.line_slice(y)))
+    }
+    fn line_slice(&self, y: usize) -> &[Cell] {
+        let from = y * self.width as usize;
+        let to = from + self.width as usize;
+        &self.cells[from..to]
+    }
+    pub fn put_char(&mut self, ch: char) {
+        let width = UnicodeWidthChar::width(ch).unwrap_or(1);
+        if width == 0 {
+            self.attach_combining(ch);
+            return;
+        }
+        if width == 2 && self.cursor.x + 1 >= self.width {
+            // A wide glyph can't be split across lines: wrap first, leaving the final
+            // column of this line blank.
+            self.cursor.x = 0;
+            self.cursor.y += 1;
+        }
+        self.extend_while_cursor_past();
+        let idx = self.cursor.index(self.width);
+        self.cells[idx] = Cell {
+            ch,
+            fg: self.pen.fg,
+            bg: self.pen.bg,
+            flags: self.pen.flags,
+            continuation: false,
+            combining: Vec::new(),
+        };
+        self.mark_dirty(self.cursor.y);
+        self.cursor.x += 1;
+        if width == 2 {
+            self.extend_while_cursor_past();
+            let idx = self.cursor.index(self.width);
+            self.cells[idx] = Cell {
+                continuation: true,
+                ..Cell::default()
+            };
+            self.mark_dirty(self.cursor.y);
+            self.cursor.x += 1;
+        }
+        if self.cursor.x >= self.width {
+            self.cursor.x = 0;
+            self.cursor.y += 1;
+        }
+    }
+    /// Attaches a zero-width combining character to whatever was written to the cell just
+    /// before the cursor, rather than consuming a column of its own.
+    fn attach_combining(&mut self, ch: char) {
+        if let Some(idx) = self.previous_cell_index() {
+            self.cells[idx].combining.push(ch);
+            self.mark_dirty(idx / self.width as usize);
+        }
+    }
+    fn previous_cell_index(&self) -> Option<usize> {
+        let (x, y) = if self.cursor.x > 0 {
+            (self.cursor.x - 1, self.cursor.y)
+        } else if self.cursor.y > 0 {
+            (self.width - 1, self.cursor.y - 1)
+        } else {
+            return None;
+        };
+        let mut idx = y * self.width as usize + x as usize;
+        if idx >= self.cells.len() {
+            return None;
+        }
+        if idx > 0 && self.cells[idx].continuation {
+            idx -= 1;
+        }
+        Some(idx)
+    }
+    fn extend(&mut self) {
+        self.cells
+            .extend(std::iter::repeat_n(Cell::default(), self.width as usize));
+        self.height += 1;
+        self.dirty.push(true);
+    }
+    /// Move the top visible line into scrollback (dropping the oldest line once
+    /// `max_scrollback` is exceeded) and shift the viewport up by one row.
+    fn scroll_up_one(&mut self) {
+        let width = self.width as usize;
+        let top_line: Vec<Cell> = self.cells.drain(0..width).collect();
+        if self.max_scrollback > 0 {
+            if self.scrollback.len() >= self.max_scrollback {
+                self.scrollback.pop_front();
+            }
+            self.scrollback.push_back(top_line);
+        }
+        self.cells
+            .extend(std::iter::repeat_n(Cell::default(), width));
+        // Every row's on-screen content shifted up by one, so a partial re-diff against the
+        // rows' old positions wouldn't make sense; mark the whole viewport dirty.
+        self.dirty.fill(true);
+    }
+    fn extend_while_cursor_past(&mut self) {
+        if self.height_limit.is_some() {
+            while self.cursor.y >= self.height {
+                self.scroll_up_one();
+                self.cursor.y -= 1;
+            }
+        } else {
+            while self.cursor.y >= self.height {
+                self.extend();
+            }
+        }
+    }
+    /// Erase part of the cursor's line: `0` from the cursor to the end of the line, `1` from
+    /// the start of the line to the cursor (inclusive), `2` the whole line. Any other mode is
+    /// treated like `0`.
+    pub fn erase_in_line(&mut self, mode: u8) {
+        let row_start = self.cursor.y * self.width as usize;
+        let row_end = (row_start + self.width as usize).min(self.cells.len());
+        let cursor = (row_start + self.cursor.x as usize).min(row_end);
+        let (from, to) = match mode {
+            1 => (row_start, (cursor + 1).min(row_end)),
+            2 => (row_start, row_end),
+            _ => (cursor, row_end),
+        };
+        if from < to {
+            self.cells[from..to].fill(Cell::default());
+        }
+        self.mark_dirty(self.cursor.y);
+    }
+    /// Erase part of the screen: `0` from the cursor to the end of the screen, `1` from the
+    /// start of the screen to the cursor, `2` the whole visible screen, `3` the whole visible
+    /// screen plus scrollback. Any other mode defaults to `2`.
+    pub fn clear(&mut self, mode: u8) {
+        let len = self.cells.len();
+        let cursor = self.cursor.index(self.width).min(len);
+        let cursor_inclusive = (cursor + 1).min(len);
+        match mode {
+            0 => self.cells[cursor..].fill(Cell::default()),
+            1 => self.cells[..cursor_inclusive].fill(Cell::default()),
+            3 => {
+                self.cells.fill(Cell::default());
+                self.scrollback.clear();
+            }
+            2 => self.cells.fill(Cell::default()),
+            _ => {
+                log::warn!("Clear mode {mode} not implemented, defaulting to mode 2.");
+                self.cells.fill(Cell::default());
+            }
+        }
+        self.dirty.fill(true);
+    }
+}
+
+/// These mirror [`Handler`](crate::parser::Handler)'s methods; [`Term`](crate::Term)
+/// implements that trait itself (so it can also intercept alt-screen switches and cursor
+/// save/restore) and forwards everything else here.
+impl TermState {
+    pub const fn carriage_return(&mut self) {
+        self.cursor.x = 0;
+    }
+    pub const fn line_feed(&mut self) {
+        self.cursor.y += 1;
+    }
+    pub const fn cursor_up(&mut self, n: u8) {
+        self.cursor.y = self.cursor.y.saturating_sub(n as usize);
+    }
+    /// Unlike [`Self::line_feed`], a direct cursor move (`CSI n B`) never scrolls: it's clamped
+    /// to the bottom of the screen in bounded mode, same as a real terminal's cursor-addressing
+    /// commands.
+    pub const fn cursor_down(&mut self, n: u8) {
+        self.cursor.y += n as usize;
+        self.clamp_cursor_to_screen();
+    }
+    pub fn cursor_left(&mut self, n: u8) {
+        self.cursor.x = self.cursor.x.saturating_sub(u16::from(n));
+    }
+    pub fn cursor_right(&mut self, n: u8) {
+        self.cursor.x += u16::from(n);
+    }
+    pub const fn cursor_cr_up(&mut self, n: u8) {
+        self.cursor.y = self.cursor.y.saturating_sub(n as usize);
+        self.cursor.x = 0;
+    }
+    /// See [`Self::cursor_down`]: clamped, not scrolling.
+    pub const fn cursor_cr_down(&mut self, n: u8) {
+        self.cursor.y += n as usize;
+        self.cursor.x = 0;
+        self.clamp_cursor_to_screen();
+    }
+    /// See [`Self::cursor_down`]: clamped, not scrolling.
+    pub fn cursor_set(&mut self, x: u8, y: u8) {
+        self.cursor.x = x.into();
+        self.cursor.y = y as usize;
+        self.clamp_cursor_to_screen();
+    }
+    /// Clamp `cursor.y` to the bottom row in bounded mode, so a cursor-addressing command can't
+    /// push the cursor past the viewport and have the next write mistaken for a genuine
+    /// line-feed scroll by [`Self::extend_while_cursor_past`]. A no-op in unbounded mode, where
+    /// [`Self::extend`] just grows the buffer to meet the cursor.
+    const fn clamp_cursor_to_screen(&mut self) {
+        if let Some(limit) = self.height_limit {
+            let max_y = limit.saturating_sub(1);
+            if self.cursor.y > max_y {
+                self.cursor.y = max_y;
+            }
+        }
+    }
+    pub fn sgr(&mut self, params: &[Option<u8>]) {
+        self.pen.apply_sgr(params);
+    }
+    pub fn log_unhandled(final_byte: u8, params: &[u8]) {
+        log::warn!(
+            "Ignored control code: '{ch}', params: {params:?}",
+            ch = final_byte as char,
+            params = std::str::from_utf8(params)
+        );
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Cursor {
+    pub x: u16,
+    pub y: usize,
+}
+
+impl Cursor {
+    const fn index(&self, width: u16) -> usize {
+        self.y * width as usize + self.x as usize
+    }
+}
+
+/// Appends the `CSI ... m` sequence that moves the running style from whatever preceded it
+/// to `pen`. Always resets first and re-applies: simpler than diffing attribute-by-attribute,
+/// and still minimal in the sense that unchanged runs emit nothing at all.
+pub fn push_sgr(buf: &mut String, pen: Pen) {
+    buf.push_str("\x1b[0");
+    if pen.flags.bold {
+        buf.push_str(";1");
+    }
+    if pen.flags.italic {
+        buf.push_str(";3");
+    }
+    if pen.flags.underline {
+        buf.push_str(";4");
+    }
+    if pen.flags.reverse {
+        buf.push_str(";7");
+    }
+    push_sgr_color(buf, pen.fg, 30, 90, 38);
+    push_sgr_color(buf, pen.bg, 40, 100, 48);
+    buf.push('m');
+}
+
+fn push_sgr_color(buf: &mut String, color: Color, base: u8, bright_base: u8, extended: u8) {
+    use std::fmt::Write as _;
+
+    match color {
+        Color::Default => {}
+        Color::Indexed(n @ 0..=7) => {
+            let _ = write!(buf, ";{}", base + n);
+        }
+        Color::Indexed(n @ 8..=15) => {
+            let _ = write!(buf, ";{}", bright_base + (n - 8));
+        }
+        Color::Indexed(n) => {
+            let _ = write!(buf, ";{extended};5;{n}");
+        }
+        Color::Rgb(r, g, b) => {
+            let _ = write!(buf, ";{extended};2;{r};{g};{b}");
+        }
+    }
+}
+
+fn cell_style(cell: &Cell) -> Option<String> {
+    use std::fmt::Write as _;
+
+    if cell.fg == Color::Default && cell.bg == Color::Default && cell.flags == CellFlags::default()
+    {
+        return None;
+    }
+    // Reverse video has no direct CSS equivalent, so swap fg/bg here the way a real terminal
+    // swaps them when painting, matching the `;7` SGR code `push_sgr` emits for the ANSI export.
+    let (fg, bg) = if cell.flags.reverse {
+        (cell.bg, cell.fg)
+    } else {
+        (cell.fg, cell.bg)
+    };
+    let mut style = String::new();
+    if let Some(css) = color_css(fg) {
+        let _ = write!(style, "color:{css};");
+    }
+    if let Some(css) = color_css(bg) {
+        let _ = write!(style, "background-color:{css};");
+    }
+    if cell.flags.bold {
+        style.push_str("font-weight:bold;");
+    }
+    if cell.flags.italic {
+        style.push_str("font-style:italic;");
+    }
+    if cell.flags.underline {
+        style.push_str("text-decoration:underline;");
+    }
+    Some(style)
+}
+
+fn color_css(color: Color) -> Option<String> {
+    use std::fmt::Write as _;
+
+    match color {
+        Color::Default => None,
+        Color::Indexed(n) => Some(format!("var(--ansi-{n})")),
+        Color::Rgb(r, g, b) => {
+            let mut s = String::new();
+            let _ = write!(s, "#{r:02x}{g:02x}{b:02x}");
+            Some(s)
+        }
+    }
+}
+
+fn push_line(buf: &mut String, line: &[Cell]) {
+    for cell in line {
+        if cell.continuation {
+            continue;
+        }
+        buf.push(cell.ch);
+        buf.extend(cell.combining.iter().copied());
+    }
+}
+
+fn push_html_escaped(buf: &mut String, ch: char) {
+    match ch {
+        '<' => buf.push_str("&lt;"),
+        '>' => buf.push_str("&gt;"),
+        '&' => buf.push_str("&amp;"),
+        c => buf.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TermState;
+
+    #[test]
+    fn absolute_cursor_move_does_not_scroll() {
+        let mut state = TermState::with_dimensions(5, 2, 10);
+        state.put_char('a');
+        state.line_feed();
+        state.carriage_return();
+        state.put_char('b');
+        state.line_feed();
+        state.carriage_return();
+        state.put_char('c');
+        assert_eq!(state.scrollback_lines(), 1);
+
+        // A cursor-addressing command that jumps far below the viewport must clamp, not scroll.
+        state.cursor_down(50);
+        state.put_char('x');
+        assert_eq!(state.scrollback_lines(), 1);
+        assert!(state.contents_to_string().contains('a'));
+        assert!(state.contents_to_string().contains('b'));
+    }
+
+    #[test]
+    fn cursor_set_past_bottom_clamps_instead_of_scrolling() {
+        let mut state = TermState::with_dimensions(5, 2, 10);
+        state.put_char('a');
+        assert_eq!(state.scrollback_lines(), 0);
+        state.cursor_set(0, 99);
+        state.put_char('x');
+        assert_eq!(state.scrollback_lines(), 0);
+        assert!(state.contents_to_string().contains('a'));
+    }
+
+    #[test]
+    fn with_dimensions_pre_filled_cells_still_count_as_empty() {
+        let state = TermState::with_dimensions(5, 3, 10);
+        assert!(
+            state.is_empty(),
+            "a freshly constructed terminal has nothing written to it, even though \
+             with_dimensions pre-fills cells to blank defaults up front"
+        );
+    }
+
+    #[test]
+    fn is_empty_becomes_false_once_something_is_written() {
+        let mut state = TermState::with_dimensions(5, 3, 10);
+        state.put_char('a');
+        assert!(!state.is_empty());
+    }
+
+    #[test]
+    fn erase_in_line_mode_1_includes_cursor_cell() {
+        let mut state = TermState::new(6);
+        for ch in "ABCDE".chars() {
+            state.put_char(ch);
+        }
+        state.cursor_set(3, 0); // under 'D'
+        state.erase_in_line(1);
+        assert_eq!(
+            state.line_slice(0)[3].ch,
+            ' ',
+            "cursor's own cell must be erased"
+        );
+        assert_eq!(
+            state.line_slice(0)[4].ch,
+            'E',
+            "cells after the cursor are untouched"
+        );
+    }
+
+    #[test]
+    fn clear_mode_1_includes_cursor_cell() {
+        let mut state = TermState::with_dimensions(6, 1, 0);
+        for ch in "ABCDE".chars() {
+            state.put_char(ch);
+        }
+        state.cursor_set(3, 0); // under 'D'
+        state.clear(1);
+        assert_eq!(state.line_slice(0)[4].ch, 'E');
+        assert_eq!(state.line_slice(0)[3].ch, ' ');
+    }
+
+    #[test]
+    fn render_diff_consumes_dirty_flags_per_call() {
+        let mut state = TermState::with_dimensions(5, 1, 0);
+        let snap = state.snapshot();
+        state.put_char('x');
+
+        // First call against `snap` sees the real change...
+        assert!(!state.render_diff(&snap).is_empty());
+        // ...but calling it again with the *same* snapshot returns nothing: the dirty flag it
+        // relied on was already consumed. A fresh `snapshot()` is required before each call.
+        assert!(state.render_diff(&snap).is_empty());
+    }
+
+    #[test]
+    fn html_export_reflects_reverse_video() {
+        let mut state = TermState::new(1);
+        state.sgr(&[Some(31), Some(7)]); // red fg, reverse
+        state.put_char('x');
+        let html = state.contents_to_html();
+        assert!(
+            html.contains("background-color:"),
+            "reverse video should show up as a swapped background in the HTML export: {html}"
+        );
+    }
+
+    #[test]
+    fn wide_glyph_occupies_two_cells() {
+        let mut state = TermState::new(4);
+        state.put_char('世');
+        assert_eq!(state.line_slice(0)[0].ch, '世');
+        assert!(state.line_slice(0)[1].continuation);
+        assert_eq!(state.cursor.x, 2);
+    }
+
+    #[test]
+    fn wide_glyph_wraps_instead_of_splitting_across_lines() {
+        let mut state = TermState::with_dimensions(2, 2, 0);
+        state.put_char('a');
+        // Only one column left on this line: the wide glyph can't fit, so it wraps whole.
+        state.put_char('世');
+        assert_eq!(state.line_slice(0)[0].ch, 'a');
+        assert_eq!(state.line_slice(0)[1].ch, ' ');
+        assert_eq!(state.line_slice(1)[0].ch, '世');
+        assert!(state.line_slice(1)[1].continuation);
+    }
+
+    #[test]
+    fn combining_char_attaches_to_previous_cell_without_taking_a_column() {
+        let mut state = TermState::new(4);
+        state.put_char('e');
+        state.put_char('\u{0301}'); // combining acute accent
+        assert_eq!(state.line_slice(0)[0].ch, 'e');
+        assert_eq!(state.line_slice(0)[0].combining, vec!['\u{0301}']);
+        assert_eq!(
+            state.cursor.x, 1,
+            "combining char must not advance the cursor"
+        );
+    }
+}